@@ -8,11 +8,15 @@ mod types;
 mod migration_test;
 
 use soroban_sdk::{contract, contractimpl, symbol_short, token, Address, Env, Vec};
-pub use types::{DataKey, LegacyStream, Stream, StreamRequest};
+pub use types::{Condition, DataKey, FlowStream, LegacyStream, Segment, Stream, StreamRequest};
 
 const THRESHOLD: u32 = 518400; // ~30 days
 const LIMIT: u32 = 1036800; // ~60 days
 
+// Current version of the `Stream` struct shape. Bump this and teach
+// `migrate_stream_in_memory` a new upgrade step whenever the struct changes.
+const CURRENT_STREAM_VERSION: u32 = 2;
+
 #[contract]
 pub struct StellarStream;
 
@@ -99,39 +103,91 @@ impl StellarStream {
             .publish((symbol_short!("migrate"), admin), target_version);
     }
 
-    /// Migration from v1 to v2: Add cliff_time to existing streams
-    /// Legacy streams (v1) didn't have cliff_time, so we set it to start_time
-    fn migrate_v1_to_v2(env: &Env) {
-        let stream_count: u64 = env
+    /// Migration from v1 to v2: adds `cliff_time` to existing streams.
+    ///
+    /// There is no bulk work to do here anymore. Upgrading every stream in
+    /// one call would need to iterate `stream_id` 1..=`StreamId`, which blows
+    /// the instruction limit on a large contract. Instead, each stream is
+    /// upgraded lazily the next time it's touched (see
+    /// `ensure_stream_migrated`), based on its own per-stream schema version
+    /// and independently of the contract version. This step does nothing;
+    /// `migrate` itself records the new contract version.
+    fn migrate_v1_to_v2(_env: &Env) {}
+
+    /// Decode a stream as the current `Stream` shape, upgrading it from
+    /// `LegacyStream` in memory if its schema is out of date. Returns
+    /// whether the upgraded value still needs to be written back, so
+    /// callers can choose whether to persist it.
+    fn migrate_stream_in_memory(env: &Env, stream_id: u64) -> (Stream, bool) {
+        let stream_key = DataKey::Stream(stream_id);
+
+        if Self::get_stream_schema_version(env, stream_id) < CURRENT_STREAM_VERSION {
+            let legacy: LegacyStream = env
+                .storage()
+                .persistent()
+                .get(&stream_key)
+                .expect("Stream does not exist");
+
+            let migrated = Stream {
+                sender: legacy.sender,
+                receiver: legacy.receiver,
+                token: legacy.token,
+                amount: legacy.amount,
+                start_time: legacy.start_time,
+                cliff_time: legacy.start_time,
+                end_time: legacy.end_time,
+                withdrawn_amount: legacy.withdrawn_amount,
+            };
+
+            return (migrated, true);
+        }
+
+        let stream = env
             .storage()
-            .instance()
-            .get(&DataKey::StreamId)
-            .unwrap_or(0);
+            .persistent()
+            .get(&stream_key)
+            .expect("Stream does not exist");
 
-        // Iterate through all existing streams
-        for stream_id in 1..=stream_count {
-            let stream_key = DataKey::Stream(stream_id);
+        (stream, false)
+    }
 
-            // Check if stream exists
-            if !env.storage().persistent().has(&stream_key) {
-                continue;
-            }
+    /// Read a stream, lazily upgrading it in place if it's still on an older
+    /// schema. Every entry point that mutates a stream goes through this
+    /// instead of reading `DataKey::Stream` directly, so legacy streams get
+    /// upgraded exactly once, the first time they're next accessed.
+    fn ensure_stream_migrated(env: &Env, stream_id: u64) -> Stream {
+        let (stream, needs_write) = Self::migrate_stream_in_memory(env, stream_id);
 
-            // Try to read as current Stream format first
-            // If it succeeds, the stream is already migrated
-            if env
-                .storage()
+        if needs_write {
+            env.storage()
                 .persistent()
-                .get::<DataKey, Stream>(&stream_key)
-                .is_some()
-            {
-                continue; // Already in new format, skip
-            }
-
-            // If reading as Stream failed, try as LegacyStream
-            // Note: In practice, we'd need to handle this more carefully
-            // For now, we'll just skip streams that can't be read
+                .set(&DataKey::Stream(stream_id), &stream);
+            Self::set_stream_schema_version(env, stream_id, CURRENT_STREAM_VERSION);
         }
+
+        stream
+    }
+
+    /// Non-mutating counterpart to `ensure_stream_migrated`, for read-only
+    /// views: returns what a stream would read as once upgraded, without
+    /// writing anything to storage.
+    fn peek_stream(env: &Env, stream_id: u64) -> Stream {
+        Self::migrate_stream_in_memory(env, stream_id).0
+    }
+
+    /// Schema version a stream was last written with. Defaults to 1 (the
+    /// original, pre-`cliff_time` shape) for streams that predate this key.
+    fn get_stream_schema_version(env: &Env, stream_id: u64) -> u32 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::StreamSchema(stream_id))
+            .unwrap_or(1)
+    }
+
+    fn set_stream_schema_version(env: &Env, stream_id: u64, version: u32) {
+        env.storage()
+            .persistent()
+            .set(&DataKey::StreamSchema(stream_id), &version);
     }
 
     /// Helper function to manually migrate a single stream (for testing/recovery)
@@ -169,6 +225,7 @@ impl StellarStream {
             env.storage()
                 .persistent()
                 .set(&stream_key, &migrated_stream);
+            Self::set_stream_schema_version(&env, stream_id, CURRENT_STREAM_VERSION);
 
             env.events()
                 .publish((symbol_short!("mig_strm"), admin), stream_id);
@@ -247,6 +304,7 @@ impl StellarStream {
         start_time: u64,
         cliff_time: u64,
         end_time: u64,
+        segments: Option<Vec<Segment>>,
     ) -> u64 {
         Self::check_not_paused(&env);
         sender.require_auth();
@@ -266,6 +324,10 @@ impl StellarStream {
         let fee_amount = (amount * fee_bps as i128) / 10000;
         let principal = amount - fee_amount;
 
+        if let Some(segments) = &segments {
+            Self::validate_segments(segments, start_time, end_time, principal);
+        }
+
         token_client.transfer(&sender, &env.current_contract_address(), &principal);
 
         if fee_amount > 0 {
@@ -302,6 +364,15 @@ impl StellarStream {
         env.storage()
             .persistent()
             .extend_ttl(&stream_key, THRESHOLD, LIMIT);
+        Self::set_stream_schema_version(&env, stream_id, CURRENT_STREAM_VERSION);
+
+        if let Some(segments) = segments {
+            let segments_key = DataKey::StreamSegments(stream_id);
+            env.storage().persistent().set(&segments_key, &segments);
+            env.storage()
+                .persistent()
+                .extend_ttl(&segments_key, THRESHOLD, LIMIT);
+        }
 
         env.events()
             .publish((symbol_short!("create"), sender), stream_id);
@@ -309,6 +380,56 @@ impl StellarStream {
         stream_id
     }
 
+    /// Validate a dynamic unlock curve: strictly increasing `end_time`s,
+    /// positive amounts, the segments must sum to the stream principal, and
+    /// the last segment must finish exactly when the stream itself does, so
+    /// `cancel_stream`'s `now >= stream.end_time` guard stays in lockstep
+    /// with the schedule `unlocked_amount` actually follows.
+    fn validate_segments(segments: &Vec<Segment>, start_time: u64, end_time: u64, principal: i128) {
+        let mut prev_end = start_time;
+        let mut total: i128 = 0;
+
+        for segment in segments.iter() {
+            if segment.amount <= 0 {
+                panic!("Segment amount must be greater than zero");
+            }
+            if segment.end_time <= prev_end {
+                panic!("Segment end times must be strictly increasing");
+            }
+            total += segment.amount;
+            prev_end = segment.end_time;
+        }
+
+        if prev_end != end_time {
+            panic!("Last segment end time must equal the stream end time");
+        }
+
+        if total != principal {
+            panic!("Segment amounts must sum to the stream principal");
+        }
+    }
+
+    /// Unlocked amount for a stream, following its dynamic segment curve if
+    /// one is attached and falling back to the single cliff-and-slope
+    /// schedule otherwise.
+    fn unlocked_amount(env: &Env, stream_id: u64, stream: &Stream, now: u64) -> i128 {
+        let segments: Option<Vec<Segment>> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::StreamSegments(stream_id));
+
+        match segments {
+            Some(segments) => math::calculate_unlocked_segments(&segments, stream.start_time, now),
+            None => math::calculate_unlocked(
+                stream.amount,
+                stream.start_time,
+                stream.cliff_time,
+                stream.end_time,
+                now,
+            ),
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn create_batch_streams(
         env: Env,
@@ -356,6 +477,7 @@ impl StellarStream {
             env.storage()
                 .persistent()
                 .set(&DataKey::Stream(stream_id), &stream);
+            Self::set_stream_schema_version(&env, stream_id, CURRENT_STREAM_VERSION);
 
             env.events()
                 .publish((symbol_short!("create"), sender.clone()), stream_id);
@@ -373,24 +495,14 @@ impl StellarStream {
         receiver.require_auth();
 
         let stream_key = DataKey::Stream(stream_id);
-        let mut stream: Stream = env
-            .storage()
-            .persistent()
-            .get(&stream_key)
-            .expect("Stream does not exist");
+        let mut stream = Self::ensure_stream_migrated(&env, stream_id);
 
         if receiver != stream.receiver {
             panic!("Unauthorized: You are not the receiver of this stream");
         }
 
         let now = env.ledger().timestamp();
-        let total_unlocked = math::calculate_unlocked(
-            stream.amount,
-            stream.start_time,
-            stream.cliff_time,
-            stream.end_time,
-            now,
-        );
+        let total_unlocked = Self::unlocked_amount(&env, stream_id, &stream, now);
 
         let withdrawable_amount = total_unlocked - stream.withdrawn_amount;
 
@@ -398,6 +510,8 @@ impl StellarStream {
             panic!("No funds available to withdraw at this time");
         }
 
+        Self::check_stream_conditions(&env, stream_id);
+
         let token_client = token::Client::new(&env, &stream.token);
         token_client.transfer(
             &env.current_contract_address(),
@@ -422,11 +536,7 @@ impl StellarStream {
     pub fn cancel_stream(env: Env, stream_id: u64) {
         Self::check_not_paused(&env);
         let stream_key = DataKey::Stream(stream_id);
-        let stream: Stream = env
-            .storage()
-            .persistent()
-            .get(&stream_key)
-            .expect("Stream does not exist");
+        let stream = Self::ensure_stream_migrated(&env, stream_id);
 
         stream.sender.require_auth();
 
@@ -436,13 +546,7 @@ impl StellarStream {
             panic!("Stream has already completed and cannot be cancelled");
         }
 
-        let total_unlocked = math::calculate_unlocked(
-            stream.amount,
-            stream.start_time,
-            stream.cliff_time,
-            stream.end_time,
-            now,
-        );
+        let total_unlocked = Self::unlocked_amount(&env, stream_id, &stream, now);
 
         let withdrawable_to_receiver = total_unlocked - stream.withdrawn_amount;
         let refund_to_sender = stream.amount - total_unlocked;
@@ -469,11 +573,7 @@ impl StellarStream {
     }
 
     pub fn transfer_receiver(env: Env, stream_id: u64, new_receiver: Address) {
-        let mut stream: Stream = env
-            .storage()
-            .persistent()
-            .get(&DataKey::Stream(stream_id))
-            .expect("Stream does not exist");
+        let mut stream = Self::ensure_stream_migrated(&env, stream_id);
 
         stream.receiver.require_auth();
 
@@ -492,4 +592,383 @@ impl StellarStream {
             .persistent()
             .extend_ttl(&stream_key, THRESHOLD, LIMIT);
     }
+
+    // ========== Flow Streams ==========
+
+    /// Open an open-ended, rate-based stream: the sender funds it with an
+    /// initial deposit and can top it up later with `deposit_to_stream`,
+    /// rather than committing a total amount up front like `create_stream`.
+    pub fn create_flow_stream(
+        env: Env,
+        sender: Address,
+        receiver: Address,
+        token: Address,
+        rate_per_second: i128,
+        deposit: i128,
+        start_time: u64,
+    ) -> u64 {
+        Self::check_not_paused(&env);
+        sender.require_auth();
+
+        if rate_per_second <= 0 {
+            panic!("Rate per second must be greater than zero");
+        }
+        if deposit <= 0 {
+            panic!("Deposit must be greater than zero");
+        }
+
+        let token_client = token::Client::new(&env, &token);
+        let fee_bps: u32 = env.storage().instance().get(&DataKey::FeeBps).unwrap_or(0);
+        let fee_amount = (deposit * fee_bps as i128) / 10000;
+        let principal = deposit - fee_amount;
+
+        token_client.transfer(&sender, &env.current_contract_address(), &principal);
+
+        if fee_amount > 0 {
+            let treasury: Address = env
+                .storage()
+                .instance()
+                .get(&DataKey::Treasury)
+                .expect("Treasury not set");
+            token_client.transfer(&sender, &treasury, &fee_amount);
+        }
+
+        let mut stream_id: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::FlowStreamId)
+            .unwrap_or(0);
+        stream_id += 1;
+        env.storage()
+            .instance()
+            .set(&DataKey::FlowStreamId, &stream_id);
+        env.storage().instance().extend_ttl(THRESHOLD, LIMIT);
+
+        let flow_stream = FlowStream {
+            sender: sender.clone(),
+            receiver,
+            token,
+            rate_per_second,
+            deposited: principal,
+            withdrawn_amount: 0,
+            last_update_time: start_time,
+            start_time,
+        };
+
+        let flow_key = DataKey::FlowStream(stream_id);
+        env.storage().persistent().set(&flow_key, &flow_stream);
+        env.storage()
+            .persistent()
+            .extend_ttl(&flow_key, THRESHOLD, LIMIT);
+
+        env.events()
+            .publish((symbol_short!("flow_new"), sender), stream_id);
+
+        stream_id
+    }
+
+    /// Top up an existing flow stream's deposited balance so the receiver
+    /// keeps accruing instead of hitting the insolvency cap.
+    pub fn deposit_to_stream(env: Env, stream_id: u64, sender: Address, amount: i128) {
+        Self::check_not_paused(&env);
+        sender.require_auth();
+
+        if amount <= 0 {
+            panic!("Amount must be greater than zero");
+        }
+
+        let flow_key = DataKey::FlowStream(stream_id);
+        let mut flow_stream: FlowStream = env
+            .storage()
+            .persistent()
+            .get(&flow_key)
+            .expect("Flow stream does not exist");
+
+        if sender != flow_stream.sender {
+            panic!("Unauthorized: You are not the sender of this flow stream");
+        }
+
+        let token_client = token::Client::new(&env, &flow_stream.token);
+        let fee_bps: u32 = env.storage().instance().get(&DataKey::FeeBps).unwrap_or(0);
+        let fee_amount = (amount * fee_bps as i128) / 10000;
+        let principal = amount - fee_amount;
+
+        token_client.transfer(&sender, &env.current_contract_address(), &principal);
+
+        if fee_amount > 0 {
+            let treasury: Address = env
+                .storage()
+                .instance()
+                .get(&DataKey::Treasury)
+                .expect("Treasury not set");
+            token_client.transfer(&sender, &treasury, &fee_amount);
+        }
+
+        flow_stream.deposited += principal;
+        flow_stream.last_update_time = env.ledger().timestamp();
+        env.storage().persistent().set(&flow_key, &flow_stream);
+        env.storage()
+            .persistent()
+            .extend_ttl(&flow_key, THRESHOLD, LIMIT);
+
+        env.events().publish(
+            (symbol_short!("flow_dep"), sender),
+            (stream_id, amount),
+        );
+    }
+
+    /// Withdraw whatever has unlocked on a flow stream so far: accrual at
+    /// `rate_per_second` since `start_time`, capped at the deposited amount
+    /// so the receiver simply stops accruing until the sender tops up.
+    pub fn withdraw_flow_stream(env: Env, stream_id: u64, receiver: Address) -> i128 {
+        Self::check_not_paused(&env);
+        receiver.require_auth();
+
+        let flow_key = DataKey::FlowStream(stream_id);
+        let mut flow_stream: FlowStream = env
+            .storage()
+            .persistent()
+            .get(&flow_key)
+            .expect("Flow stream does not exist");
+
+        if receiver != flow_stream.receiver {
+            panic!("Unauthorized: You are not the receiver of this flow stream");
+        }
+
+        let now = env.ledger().timestamp();
+        let total_unlocked = math::calculate_flow_unlocked(
+            flow_stream.rate_per_second,
+            flow_stream.deposited,
+            flow_stream.start_time,
+            now,
+        );
+
+        let withdrawable_amount = total_unlocked - flow_stream.withdrawn_amount;
+        if withdrawable_amount <= 0 {
+            panic!("No funds available to withdraw at this time");
+        }
+
+        let token_client = token::Client::new(&env, &flow_stream.token);
+        token_client.transfer(
+            &env.current_contract_address(),
+            &receiver,
+            &withdrawable_amount,
+        );
+
+        flow_stream.withdrawn_amount += withdrawable_amount;
+        flow_stream.last_update_time = now;
+        env.storage().persistent().set(&flow_key, &flow_stream);
+        env.storage()
+            .persistent()
+            .extend_ttl(&flow_key, THRESHOLD, LIMIT);
+
+        env.events().publish(
+            (symbol_short!("flow_wd"), receiver),
+            (stream_id, withdrawable_amount),
+        );
+
+        withdrawable_amount
+    }
+
+    /// Timestamp at which a flow stream runs out of deposited funds, i.e.
+    /// when it stops unlocking new tokens unless topped up.
+    pub fn solvent_until(env: Env, stream_id: u64) -> u64 {
+        let flow_stream: FlowStream = env
+            .storage()
+            .persistent()
+            .get(&DataKey::FlowStream(stream_id))
+            .expect("Flow stream does not exist");
+
+        flow_stream.start_time + (flow_stream.deposited / flow_stream.rate_per_second) as u64
+    }
+
+    // ========== Conditional Unlocking ==========
+
+    /// Gate a stream's withdrawals on a `Condition` in addition to its time
+    /// schedule. Only the stream's sender can set or change it.
+    pub fn set_stream_condition(env: Env, sender: Address, stream_id: u64, condition: Condition) {
+        sender.require_auth();
+
+        let stream = Self::ensure_stream_migrated(&env, stream_id);
+        if sender != stream.sender {
+            panic!("Unauthorized: Only the sender can set a stream condition");
+        }
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::StreamCondition(stream_id), &condition);
+    }
+
+    /// Record that `witness_signer` has signed off on a stream, satisfying
+    /// any `Condition::Signature(witness_signer)` gating it.
+    pub fn apply_witness(env: Env, stream_id: u64, witness_signer: Address) {
+        witness_signer.require_auth();
+
+        let witness_key = DataKey::StreamWitness(stream_id);
+        let mut witnesses: Vec<Address> = env
+            .storage()
+            .persistent()
+            .get(&witness_key)
+            .unwrap_or_else(|| Vec::new(&env));
+
+        if !witnesses.contains(&witness_signer) {
+            witnesses.push_back(witness_signer);
+            env.storage().persistent().set(&witness_key, &witnesses);
+        }
+    }
+
+    /// Panics if the stream has a `Condition` attached and it isn't fully
+    /// satisfied yet, even when time-unlocked tokens are available.
+    fn check_stream_conditions(env: &Env, stream_id: u64) {
+        if !Self::stream_conditions_met(env, stream_id) {
+            panic!("Stream condition not yet satisfied");
+        }
+    }
+
+    /// Whether a stream's attached `Condition`, if any, is fully satisfied.
+    /// A stream with no condition attached is always considered satisfied.
+    fn stream_conditions_met(env: &Env, stream_id: u64) -> bool {
+        let condition: Option<Condition> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::StreamCondition(stream_id));
+
+        match condition {
+            Some(condition) => {
+                let now = env.ledger().timestamp();
+                let witnesses: Vec<Address> = env
+                    .storage()
+                    .persistent()
+                    .get(&DataKey::StreamWitness(stream_id))
+                    .unwrap_or_else(|| Vec::new(env));
+
+                Self::condition_met(&condition, now, &witnesses)
+            }
+            None => true,
+        }
+    }
+
+    fn condition_met(condition: &Condition, now: u64, witnesses: &Vec<Address>) -> bool {
+        match condition {
+            Condition::Timestamp(t) => now >= *t,
+            Condition::Signature(signer) => witnesses.contains(signer),
+            Condition::And(conditions) => conditions
+                .iter()
+                .all(|c| Self::condition_met(&c, now, witnesses)),
+            Condition::Or(conditions) => conditions
+                .iter()
+                .any(|c| Self::condition_met(&c, now, witnesses)),
+        }
+    }
+
+    // ========== Batch Withdrawals ==========
+
+    /// Withdraw from many streams in one call, authorizing the receiver
+    /// once and emitting a single aggregated event per token. Streams with
+    /// nothing withdrawable are skipped rather than panicking, so a
+    /// partially-ready batch still succeeds.
+    pub fn withdraw_batch(env: Env, stream_ids: Vec<u64>, receiver: Address) -> Vec<(Address, i128)> {
+        Self::check_not_paused(&env);
+        receiver.require_auth();
+
+        let now = env.ledger().timestamp();
+        let mut totals: Vec<(Address, i128)> = Vec::new(&env);
+
+        for stream_id in stream_ids.iter() {
+            let stream_key = DataKey::Stream(stream_id);
+            if !env.storage().persistent().has(&stream_key) {
+                continue;
+            }
+
+            let mut stream = Self::ensure_stream_migrated(&env, stream_id);
+
+            if receiver != stream.receiver {
+                panic!("Unauthorized: You are not the receiver of this stream");
+            }
+
+            let total_unlocked = Self::unlocked_amount(&env, stream_id, &stream, now);
+            let withdrawable_amount = total_unlocked - stream.withdrawn_amount;
+
+            if withdrawable_amount <= 0 {
+                continue;
+            }
+
+            if !Self::stream_conditions_met(&env, stream_id) {
+                continue;
+            }
+
+            stream.withdrawn_amount += withdrawable_amount;
+            env.storage().persistent().set(&stream_key, &stream);
+            env.storage()
+                .persistent()
+                .extend_ttl(&stream_key, THRESHOLD, LIMIT);
+
+            Self::add_to_totals(&mut totals, stream.token, withdrawable_amount);
+        }
+
+        for (token, amount) in totals.iter() {
+            let token_client = token::Client::new(&env, &token);
+            token_client.transfer(&env.current_contract_address(), &receiver, &amount);
+
+            env.events().publish(
+                (symbol_short!("wd_batch"), receiver.clone()),
+                (token, amount),
+            );
+        }
+
+        totals
+    }
+
+    /// Read-only view of what a receiver could currently claim across many
+    /// streams, aggregated by token, so a front-end can show a single
+    /// "claim all" figure without calling `withdraw` per stream. Skips
+    /// missing streams, streams with nothing withdrawable and streams whose
+    /// condition is unmet, as `withdraw_batch` does. Streams belonging to
+    /// another receiver are skipped here, whereas `withdraw_batch` panics
+    /// on them.
+    pub fn get_withdrawable_total(
+        env: Env,
+        receiver: Address,
+        stream_ids: Vec<u64>,
+    ) -> Vec<(Address, i128)> {
+        let now = env.ledger().timestamp();
+        let mut totals: Vec<(Address, i128)> = Vec::new(&env);
+
+        for stream_id in stream_ids.iter() {
+            let stream_key = DataKey::Stream(stream_id);
+            if !env.storage().persistent().has(&stream_key) {
+                continue;
+            }
+
+            let stream = Self::peek_stream(&env, stream_id);
+            if receiver != stream.receiver {
+                continue;
+            }
+
+            let total_unlocked = Self::unlocked_amount(&env, stream_id, &stream, now);
+            let withdrawable_amount = total_unlocked - stream.withdrawn_amount;
+            if withdrawable_amount <= 0 {
+                continue;
+            }
+
+            if !Self::stream_conditions_met(&env, stream_id) {
+                continue;
+            }
+
+            Self::add_to_totals(&mut totals, stream.token, withdrawable_amount);
+        }
+
+        totals
+    }
+
+    fn add_to_totals(totals: &mut Vec<(Address, i128)>, token: Address, amount: i128) {
+        for i in 0..totals.len() {
+            let (existing_token, existing_amount) = totals.get(i).unwrap();
+            if existing_token == token {
+                totals.set(i, (existing_token, existing_amount + amount));
+                return;
+            }
+        }
+        totals.push_back((token, amount));
+    }
 }
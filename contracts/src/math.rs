@@ -0,0 +1,127 @@
+//! Pure unlock-schedule math, kept free of storage/auth concerns so it can
+//! be unit tested in isolation from the contract's entry points.
+
+use crate::types::Segment;
+use soroban_sdk::Vec;
+
+/// Unlocked amount for a fixed-amount stream with a cliff and a linear
+/// slope from `start_time` to `end_time`. Nothing unlocks before the cliff;
+/// at the cliff, the share accrued since `start_time` unlocks at once. The
+/// full `amount` is unlocked once `now >= end_time`.
+pub fn calculate_unlocked(
+    amount: i128,
+    start_time: u64,
+    cliff_time: u64,
+    end_time: u64,
+    now: u64,
+) -> i128 {
+    if now < cliff_time {
+        return 0;
+    }
+    if now >= end_time {
+        return amount;
+    }
+
+    let elapsed = (now - start_time) as i128;
+    let duration = (end_time - start_time) as i128;
+    (amount * elapsed) / duration
+}
+
+/// Unlocked amount for an open-ended, rate-based flow stream: tokens accrue
+/// continuously at `rate_per_second` from `start_time`, capped at whatever
+/// has actually been deposited so withdrawals can never outrun funding.
+pub fn calculate_flow_unlocked(
+    rate_per_second: i128,
+    deposited: i128,
+    start_time: u64,
+    now: u64,
+) -> i128 {
+    if now <= start_time {
+        return 0;
+    }
+
+    let elapsed = (now - start_time) as i128;
+    let accrued = rate_per_second * elapsed;
+
+    if accrued > deposited {
+        deposited
+    } else {
+        accrued
+    }
+}
+
+/// Unlocked amount for a Sablier-style dynamic stream made of ordered,
+/// piecewise-linear `segments`. Every fully-elapsed segment unlocks in
+/// full; the segment containing `now` interpolates linearly from its
+/// start; segments after that contribute nothing yet. Returns the full
+/// principal once `now` is at or past the last segment's `end_time`.
+pub fn calculate_unlocked_segments(segments: &Vec<Segment>, start_time: u64, now: u64) -> i128 {
+    let mut prior_sum: i128 = 0;
+    let mut seg_start = start_time;
+
+    for segment in segments.iter() {
+        if now >= segment.end_time {
+            prior_sum += segment.amount;
+            seg_start = segment.end_time;
+            continue;
+        }
+
+        if now <= seg_start {
+            return prior_sum;
+        }
+
+        let elapsed = (now - seg_start) as i128;
+        let duration = (segment.end_time - seg_start) as i128;
+        return prior_sum + (segment.amount * elapsed) / duration;
+    }
+
+    prior_sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_sdk::Env;
+
+    fn segment(amount: i128, end_time: u64) -> Segment {
+        Segment { amount, end_time }
+    }
+
+    #[test]
+    fn calculate_unlocked_segments_unlocks_fully_elapsed_segments_in_full() {
+        let env = Env::default();
+        let segments = Vec::from_array(&env, [segment(100, 100), segment(300, 400)]);
+
+        assert_eq!(calculate_unlocked_segments(&segments, 0, 0), 0);
+        assert_eq!(calculate_unlocked_segments(&segments, 0, 100), 100);
+    }
+
+    #[test]
+    fn calculate_unlocked_segments_interpolates_within_the_active_segment() {
+        let env = Env::default();
+        let segments = Vec::from_array(&env, [segment(100, 100), segment(300, 400)]);
+
+        // 100 from the first (fully elapsed) segment, plus half of the
+        // second segment's 300 (150 elapsed out of 300 seconds).
+        assert_eq!(calculate_unlocked_segments(&segments, 0, 250), 250);
+    }
+
+    #[test]
+    fn calculate_unlocked_segments_returns_full_principal_past_the_last_end_time() {
+        let env = Env::default();
+        let segments = Vec::from_array(&env, [segment(100, 100), segment(300, 400)]);
+
+        assert_eq!(calculate_unlocked_segments(&segments, 0, 400), 400);
+        assert_eq!(calculate_unlocked_segments(&segments, 0, 1_000), 400);
+    }
+
+    #[test]
+    fn calculate_unlocked_segments_handles_a_non_zero_stream_start() {
+        let env = Env::default();
+        let segments = Vec::from_array(&env, [segment(200, 300)]);
+
+        assert_eq!(calculate_unlocked_segments(&segments, 200, 200), 0);
+        assert_eq!(calculate_unlocked_segments(&segments, 200, 250), 100);
+        assert_eq!(calculate_unlocked_segments(&segments, 200, 300), 200);
+    }
+}
@@ -0,0 +1,270 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::Env;
+
+fn create_token_contract<'a>(env: &Env, admin: &Address) -> token::Client<'a> {
+    let contract_address = env.register_stellar_asset_contract(admin.clone());
+    token::Client::new(env, &contract_address)
+}
+
+fn advance_ledger(env: &Env, seconds: u64) {
+    env.ledger().with_mut(|li| {
+        li.timestamp += seconds;
+    });
+}
+
+struct TestContext<'a> {
+    env: Env,
+    client: StellarStreamClient<'a>,
+    token: token::Client<'a>,
+    sender: Address,
+    receiver: Address,
+}
+
+fn setup<'a>() -> TestContext<'a> {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let receiver = Address::generate(&env);
+
+    let token_admin = Address::generate(&env);
+    let token = create_token_contract(&env, &token_admin);
+    token.mint(&sender, &1_000_000);
+
+    let contract_id = env.register_contract(None, StellarStream);
+    let client = StellarStreamClient::new(&env, &contract_id);
+    client.initialize(&admin);
+
+    TestContext {
+        env,
+        client,
+        token,
+        sender,
+        receiver,
+    }
+}
+
+#[test]
+fn flow_stream_withdrawal_caps_at_deposited_amount() {
+    let ctx = setup();
+
+    let stream_id = ctx.client.create_flow_stream(
+        &ctx.sender,
+        &ctx.receiver,
+        &ctx.token.address,
+        &10,
+        &1_000,
+        &0,
+    );
+
+    // At rate 10/s, 200 seconds would accrue 2_000, but only 1_000 was
+    // ever deposited, so the receiver should stop accruing at the cap.
+    advance_ledger(&ctx.env, 200);
+
+    let withdrawn = ctx.client.withdraw_flow_stream(&stream_id, &ctx.receiver);
+    assert_eq!(withdrawn, 1_000);
+    assert_eq!(ctx.token.balance(&ctx.receiver), 1_000);
+}
+
+#[test]
+fn flow_stream_topup_unlocks_further_accrual_past_the_prior_cap() {
+    let ctx = setup();
+
+    let stream_id =
+        ctx.client
+            .create_flow_stream(&ctx.sender, &ctx.receiver, &ctx.token.address, &10, &500, &0);
+
+    // Accrual hits the insolvency cap at 500 well before 100 seconds.
+    advance_ledger(&ctx.env, 100);
+    assert_eq!(ctx.client.withdraw_flow_stream(&stream_id, &ctx.receiver), 500);
+
+    // Topping up lets the receiver keep accruing instead of staying capped.
+    ctx.client.deposit_to_stream(&stream_id, &ctx.sender, &1_000);
+    advance_ledger(&ctx.env, 50);
+
+    let withdrawn = ctx.client.withdraw_flow_stream(&stream_id, &ctx.receiver);
+    assert_eq!(withdrawn, 1_000);
+    assert_eq!(ctx.token.balance(&ctx.receiver), 1_500);
+}
+
+#[test]
+fn solvent_until_reflects_the_deposited_runway() {
+    let ctx = setup();
+
+    let stream_id =
+        ctx.client
+            .create_flow_stream(&ctx.sender, &ctx.receiver, &ctx.token.address, &10, &1_000, &0);
+
+    assert_eq!(ctx.client.solvent_until(&stream_id), 100);
+}
+
+#[test]
+#[should_panic(expected = "Stream condition not yet satisfied")]
+fn withdraw_panics_while_a_signature_condition_is_unmet() {
+    let ctx = setup();
+
+    let stream_id = ctx.client.create_stream(
+        &ctx.sender,
+        &ctx.receiver,
+        &ctx.token.address,
+        &1_000,
+        &0,
+        &0,
+        &1_000,
+        &None,
+    );
+
+    let reviewer = Address::generate(&ctx.env);
+    ctx.client
+        .set_stream_condition(&ctx.sender, &stream_id, &Condition::Signature(reviewer));
+
+    advance_ledger(&ctx.env, 500);
+
+    ctx.client.withdraw(&stream_id, &ctx.receiver);
+}
+
+#[test]
+fn withdraw_succeeds_once_the_witness_signs() {
+    let ctx = setup();
+
+    let stream_id = ctx.client.create_stream(
+        &ctx.sender,
+        &ctx.receiver,
+        &ctx.token.address,
+        &1_000,
+        &0,
+        &0,
+        &1_000,
+        &None,
+    );
+
+    let reviewer = Address::generate(&ctx.env);
+    ctx.client.set_stream_condition(
+        &ctx.sender,
+        &stream_id,
+        &Condition::Signature(reviewer.clone()),
+    );
+
+    advance_ledger(&ctx.env, 500);
+    ctx.client.apply_witness(&stream_id, &reviewer);
+
+    let withdrawn = ctx.client.withdraw(&stream_id, &ctx.receiver);
+    assert_eq!(withdrawn, 500);
+}
+
+#[test]
+fn cancel_stream_refunds_the_sender_regardless_of_an_unmet_condition() {
+    let ctx = setup();
+
+    let stream_id = ctx.client.create_stream(
+        &ctx.sender,
+        &ctx.receiver,
+        &ctx.token.address,
+        &1_000,
+        &0,
+        &0,
+        &1_000,
+        &None,
+    );
+
+    let reviewer = Address::generate(&ctx.env);
+    ctx.client
+        .set_stream_condition(&ctx.sender, &stream_id, &Condition::Signature(reviewer));
+
+    advance_ledger(&ctx.env, 500);
+
+    // Cancelling doesn't go through check_stream_conditions, so the
+    // unmet Signature condition doesn't block the refund.
+    ctx.client.cancel_stream(&stream_id);
+
+    assert_eq!(ctx.token.balance(&ctx.receiver), 500);
+    assert_eq!(ctx.token.balance(&ctx.sender), 1_000_000 - 500);
+}
+
+#[test]
+fn create_stream_unlocks_along_the_attached_segment_curve() {
+    let ctx = setup();
+
+    let segments = Vec::from_array(
+        &ctx.env,
+        [
+            Segment {
+                amount: 100,
+                end_time: 100,
+            },
+            Segment {
+                amount: 900,
+                end_time: 1_000,
+            },
+        ],
+    );
+
+    let stream_id = ctx.client.create_stream(
+        &ctx.sender,
+        &ctx.receiver,
+        &ctx.token.address,
+        &1_000,
+        &0,
+        &0,
+        &1_000,
+        &Some(segments),
+    );
+
+    // First segment fully elapsed (100) plus half of the second (450).
+    advance_ledger(&ctx.env, 550);
+    assert_eq!(ctx.client.withdraw(&stream_id, &ctx.receiver), 550);
+}
+
+#[test]
+#[should_panic(expected = "Segment amounts must sum to the stream principal")]
+fn create_stream_rejects_segments_that_dont_sum_to_the_principal() {
+    let ctx = setup();
+
+    let segments = Vec::from_array(
+        &ctx.env,
+        [Segment {
+            amount: 500,
+            end_time: 1_000,
+        }],
+    );
+
+    ctx.client.create_stream(
+        &ctx.sender,
+        &ctx.receiver,
+        &ctx.token.address,
+        &1_000,
+        &0,
+        &0,
+        &1_000,
+        &Some(segments),
+    );
+}
+
+#[test]
+#[should_panic(expected = "Last segment end time must equal the stream end time")]
+fn create_stream_rejects_segments_that_dont_cover_the_full_stream_end_time() {
+    let ctx = setup();
+
+    let segments = Vec::from_array(
+        &ctx.env,
+        [Segment {
+            amount: 1_000,
+            end_time: 500,
+        }],
+    );
+
+    ctx.client.create_stream(
+        &ctx.sender,
+        &ctx.receiver,
+        &ctx.token.address,
+        &1_000,
+        &0,
+        &0,
+        &1_000,
+        &Some(segments),
+    );
+}
@@ -1,4 +1,4 @@
-use soroban_sdk::{contracttype, Address};
+use soroban_sdk::{contracttype, Address, Vec};
 
 // Current version of the Stream struct
 #[contracttype]
@@ -38,6 +38,45 @@ pub struct StreamRequest {
     pub end_time: u64,
 }
 
+// Open-ended, rate-based stream: the sender deposits funds over time rather
+// than committing a total up front, and the receiver accrues at a fixed
+// rate per second, capped by whatever has actually been deposited.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlowStream {
+    pub sender: Address,
+    pub receiver: Address,
+    pub token: Address,
+    pub rate_per_second: i128,
+    pub deposited: i128,
+    pub withdrawn_amount: i128,
+    pub last_update_time: u64,
+    pub start_time: u64,
+}
+
+// One leg of a piecewise-linear unlock curve: `amount` unlocks linearly
+// from the previous segment's end_time (or the stream's start_time, for
+// the first segment) up to this segment's end_time.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Segment {
+    pub amount: i128,
+    pub end_time: u64,
+}
+
+// Gate on a stream beyond its time schedule, turning it into a general
+// escrow: e.g. milestone vesting that only releases once a designated
+// reviewer signs. `Signature` names an address that must record a witness
+// via `apply_witness`; `And`/`Or` compose other conditions.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Condition {
+    Timestamp(u64),
+    Signature(Address),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum DataKey {
@@ -49,4 +88,10 @@ pub enum DataKey {
     IsPaused,
     ContractVersion,        // Tracks current contract version
     MigrationExecuted(u32), // Tracks which migrations have been executed
+    StreamSchema(u64),      // Per-stream schema version, for lazy migration
+    FlowStream(u64),
+    FlowStreamId,
+    StreamCondition(u64), // Optional Condition gating a stream's withdrawals
+    StreamWitness(u64),   // Addresses that have satisfied a Signature condition
+    StreamSegments(u64),  // Optional piecewise-linear unlock curve for a stream
 }